@@ -10,19 +10,33 @@
 //! to `make_replayer`, an online connection will be used as the event source.
 
 use std::{
+    collections::VecDeque,
     error::Error,
     fs::File,
-    io::{Read, Write},
+    io::{Read, Seek, SeekFrom, Write},
+    mem,
     net::{TcpListener, TcpStream},
     path::Path,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use timely::{
     communication::allocator::Generic,
-    dataflow::operators::capture::{event::EventPusher, Event, EventReader, EventWriter},
-    logging::{TimelyEvent, WorkerIdentifier},
+    dataflow::{
+        operators::{
+            capture::{
+                event::{EventIterator, EventPusher},
+                Event, EventReader, EventWriter, Replay,
+            },
+            Exchange, Map,
+        },
+        Scope, Stream,
+    },
+    logging::{
+        ChannelsEvent, MessagesEvent, OperatesEvent, ProgressEvent, ScheduleEvent, StartStop,
+        TimelyEvent,
+    },
     worker::Worker,
 };
 
@@ -30,8 +44,11 @@ use logformat::pair::Pair;
 
 use TimelyEvent::{Channels, Messages, Operates, Progress, Schedule, Text};
 
-/// A replayer that reads data to be streamed into timely
-pub type Replayer<R> = EventReader<Pair<u64, Duration>, (Duration, WorkerIdentifier, TimelyEvent), R>;
+/// A replayer that reads data to be streamed into timely. Each streamed `D`
+/// is a whole `FlatEventBuffer` -- a region-allocated batch of logged
+/// events -- rather than a single event, so that decoding a batch doesn't
+/// require rematerializing one owned tuple per original event.
+pub type Replayer<R> = EventReader<Pair<u64, Duration>, FlatEventBuffer, R>;
 
 /// Types of replayer to be created from `make_replayers`
 pub enum ReplayerType {
@@ -50,51 +67,1359 @@ impl Read for ReplayerType {
     }
 }
 
+/// An `(offset, len)` reference into one of `FlatEventBuffer`'s side arenas.
+#[derive(Debug, Clone, Copy)]
+struct ArenaSlice {
+    offset: u32,
+    len: u32,
+}
+
+/// Fixed-size per-event metadata recorded by `FlatEventBuffer`. Variable-length
+/// payloads (operator/channel addresses, progress message deltas, `Text`
+/// strings) are interned into the buffer's side arenas and referenced here by
+/// an `ArenaSlice` instead of being individually heap-allocated.
+#[derive(Debug, Clone, Copy)]
+enum FlatEventKind {
+    Operates {
+        id: usize,
+        addr: ArenaSlice,
+        name: ArenaSlice,
+    },
+    Channels {
+        id: usize,
+        scope_addr: ArenaSlice,
+        source: (usize, usize),
+        target: (usize, usize),
+    },
+    Schedule {
+        id: usize,
+        start: bool,
+    },
+    Messages {
+        is_send: bool,
+        channel: usize,
+        source: usize,
+        target: usize,
+        seq_no: usize,
+        length: usize,
+    },
+    Progress {
+        is_send: bool,
+        source: usize,
+        channel: usize,
+        seq_no: usize,
+        addr: ArenaSlice,
+        messages: ArenaSlice,
+    },
+    Text(ArenaSlice),
+}
+
+/// A borrowed view over one event recorded in a `FlatEventBuffer`. Payload
+/// slices are resolved against the buffer's arenas on demand, so decoding a
+/// batch for `pag::create_pag` doesn't require rematerializing an owned
+/// `TimelyEvent` per entry.
+#[derive(Debug, Clone, Copy)]
+pub enum FlatEventView<'a> {
+    Operates {
+        id: usize,
+        addr: &'a [usize],
+        name: &'a str,
+    },
+    Channels {
+        id: usize,
+        scope_addr: &'a [usize],
+        source: (usize, usize),
+        target: (usize, usize),
+    },
+    Schedule {
+        id: usize,
+        start: bool,
+    },
+    Messages {
+        is_send: bool,
+        channel: usize,
+        source: usize,
+        target: usize,
+        seq_no: usize,
+        length: usize,
+    },
+    Progress {
+        is_send: bool,
+        source: usize,
+        channel: usize,
+        seq_no: usize,
+        addr: &'a [usize],
+        messages: &'a [(usize, usize, i64)],
+    },
+    Text(&'a str),
+}
+
+impl<'a> FlatEventView<'a> {
+    /// Reconstitutes this borrowed view into an owned `TimelyEvent`. Used at
+    /// the exchange boundary (`exchange_by_source_worker`), where an event
+    /// moving to a different worker needs to outlive the `FlatEventBuffer`
+    /// arenas it was decoded from.
+    pub fn to_owned_event(&self) -> TimelyEvent {
+        match *self {
+            FlatEventView::Operates { id, addr, name } => Operates(OperatesEvent {
+                id,
+                addr: addr.to_vec(),
+                name: name.to_owned(),
+            }),
+            FlatEventView::Channels {
+                id,
+                scope_addr,
+                source,
+                target,
+            } => Channels(ChannelsEvent {
+                id,
+                scope_addr: scope_addr.to_vec(),
+                source,
+                target,
+            }),
+            FlatEventView::Schedule { id, start } => Schedule(ScheduleEvent {
+                id,
+                start_stop: if start {
+                    StartStop::Start
+                } else {
+                    StartStop::Stop
+                },
+            }),
+            FlatEventView::Messages {
+                is_send,
+                channel,
+                source,
+                target,
+                seq_no,
+                length,
+            } => Messages(MessagesEvent {
+                is_send,
+                channel,
+                source,
+                target,
+                seq_no,
+                length,
+            }),
+            FlatEventView::Progress {
+                is_send,
+                source,
+                channel,
+                seq_no,
+                addr,
+                messages,
+            } => Progress(ProgressEvent {
+                is_send,
+                source,
+                channel,
+                seq_no,
+                addr: addr.to_vec(),
+                messages: messages.to_vec(),
+            }),
+            FlatEventView::Text(s) => Text(s.to_owned()),
+        }
+    }
+}
+
+/// A region-allocated, columnar batch of logged PAG events: fixed-size
+/// per-event metadata lives in `events`, with the variable-length payloads
+/// that `Operates`/`Channels`/`Progress`/`Text` events carry
+/// (addresses, message deltas, strings) interned into flat side arenas
+/// instead of being individually heap-allocated. Filling a whole batch this
+/// way costs a handful of (amortized) allocations -- one per arena -- rather
+/// than one per event, analogous to a columnar `FlatStack`.
+///
+/// Construct with `FlatEventBuffer::new`/`Default`, append logged events with
+/// `push`, and decode with `iter`.
+#[derive(Debug, Default, Clone)]
+pub struct FlatEventBuffer {
+    events: Vec<(Duration, usize, FlatEventKind)>,
+    addrs: Vec<usize>,
+    text: Vec<u8>,
+    messages: Vec<(usize, usize, i64)>,
+}
+
+impl FlatEventBuffer {
+    /// Creates an empty buffer.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The number of events recorded in this buffer.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether this buffer holds no events.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Records one logged event, interning any variable-length payload it
+    /// carries into the shared arenas. Event kinds `create_pag` doesn't need
+    /// (anything other than `Operates`/`Channels`/`Schedule`/`Messages`/
+    /// `Progress`/`Text`) are silently dropped, mirroring `log_pag`'s filter.
+    pub fn push(&mut self, time: Duration, worker: usize, event: TimelyEvent) {
+        let kind = match event {
+            Operates(e) => {
+                let addr = self.intern_addr(&e.addr);
+                let name = self.intern_text(&e.name);
+                FlatEventKind::Operates {
+                    id: e.id,
+                    addr,
+                    name,
+                }
+            }
+            Channels(e) => {
+                let scope_addr = self.intern_addr(&e.scope_addr);
+                FlatEventKind::Channels {
+                    id: e.id,
+                    scope_addr,
+                    source: e.source,
+                    target: e.target,
+                }
+            }
+            Schedule(e) => FlatEventKind::Schedule {
+                id: e.id,
+                start: matches!(e.start_stop, StartStop::Start),
+            },
+            Messages(e) => FlatEventKind::Messages {
+                is_send: e.is_send,
+                channel: e.channel,
+                source: e.source,
+                target: e.target,
+                seq_no: e.seq_no,
+                length: e.length,
+            },
+            Progress(e) => {
+                let addr = self.intern_addr(&e.addr);
+                let messages = self.intern_messages(&e.messages);
+                FlatEventKind::Progress {
+                    is_send: e.is_send,
+                    source: e.source,
+                    channel: e.channel,
+                    seq_no: e.seq_no,
+                    addr,
+                    messages,
+                }
+            }
+            Text(s) => FlatEventKind::Text(self.intern_text(&s)),
+            _ => return,
+        };
+        self.events.push((time, worker, kind));
+    }
+
+    /// Removes all `Schedule` entries from this batch in place, returning the
+    /// (approximate) number of bytes freed. `Schedule` is the highest-volume,
+    /// most compressible event kind, so it's the first thing shed under
+    /// sustained back-pressure (see `RateLimitedWriter`).
+    pub fn strip_schedule(&mut self) -> usize {
+        let before = self.events.len();
+        self.events
+            .retain(|(_, _, kind)| !matches!(kind, FlatEventKind::Schedule { .. }));
+        (before - self.events.len()) * mem::size_of::<(Duration, usize, FlatEventKind)>()
+    }
+
+    /// Iterates over the recorded events by reference, resolving arena slices
+    /// lazily rather than rematerializing owned `TimelyEvent`s.
+    pub fn iter(&self) -> impl Iterator<Item = (Duration, usize, FlatEventView<'_>)> {
+        self.events.iter().map(move |&(time, worker, kind)| {
+            let view = match kind {
+                FlatEventKind::Operates { id, addr, name } => FlatEventView::Operates {
+                    id,
+                    addr: self.addr_slice(addr),
+                    name: self.text_slice(name),
+                },
+                FlatEventKind::Channels {
+                    id,
+                    scope_addr,
+                    source,
+                    target,
+                } => FlatEventView::Channels {
+                    id,
+                    scope_addr: self.addr_slice(scope_addr),
+                    source,
+                    target,
+                },
+                FlatEventKind::Schedule { id, start } => FlatEventView::Schedule { id, start },
+                FlatEventKind::Messages {
+                    is_send,
+                    channel,
+                    source,
+                    target,
+                    seq_no,
+                    length,
+                } => FlatEventView::Messages {
+                    is_send,
+                    channel,
+                    source,
+                    target,
+                    seq_no,
+                    length,
+                },
+                FlatEventKind::Progress {
+                    is_send,
+                    source,
+                    channel,
+                    seq_no,
+                    addr,
+                    messages,
+                } => FlatEventView::Progress {
+                    is_send,
+                    source,
+                    channel,
+                    seq_no,
+                    addr: self.addr_slice(addr),
+                    messages: self.messages_slice(messages),
+                },
+                FlatEventKind::Text(s) => FlatEventView::Text(self.text_slice(s)),
+            };
+            (time, worker, view)
+        })
+    }
+
+    fn intern_addr(&mut self, addr: &[usize]) -> ArenaSlice {
+        let offset = self.addrs.len() as u32;
+        self.addrs.extend_from_slice(addr);
+        ArenaSlice {
+            offset,
+            len: addr.len() as u32,
+        }
+    }
+
+    fn intern_text(&mut self, s: &str) -> ArenaSlice {
+        let offset = self.text.len() as u32;
+        self.text.extend_from_slice(s.as_bytes());
+        ArenaSlice {
+            offset,
+            len: s.len() as u32,
+        }
+    }
+
+    fn intern_messages(&mut self, messages: &[(usize, usize, i64)]) -> ArenaSlice {
+        let offset = self.messages.len() as u32;
+        self.messages.extend_from_slice(messages);
+        ArenaSlice {
+            offset,
+            len: messages.len() as u32,
+        }
+    }
+
+    fn addr_slice(&self, s: ArenaSlice) -> &[usize] {
+        &self.addrs[s.offset as usize..(s.offset + s.len) as usize]
+    }
+
+    fn messages_slice(&self, s: ArenaSlice) -> &[(usize, usize, i64)] {
+        &self.messages[s.offset as usize..(s.offset + s.len) as usize]
+    }
+
+    fn text_slice(&self, s: ArenaSlice) -> &str {
+        std::str::from_utf8(&self.text[s.offset as usize..(s.offset + s.len) as usize])
+            .unwrap_or("")
+    }
+}
+
+/// Approximate serialized-size accounting, used by the transport tuning layer
+/// (`CoalescingWriter`, `RateLimitedWriter`) to decide when to flush or
+/// throttle without fully serializing a batch just to measure it.
+pub trait ApproxBytes {
+    /// An approximation of this value's serialized size, in bytes.
+    fn approx_bytes(&self) -> usize;
+}
+
+impl ApproxBytes for FlatEventBuffer {
+    fn approx_bytes(&self) -> usize {
+        self.events.len() * mem::size_of::<(Duration, usize, FlatEventKind)>()
+            + self.addrs.len() * mem::size_of::<usize>()
+            + self.text.len()
+            + self.messages.len() * mem::size_of::<(usize, usize, i64)>()
+    }
+}
+
+/// Transport tuning for the logging TCP path.
+///
+/// `LowLatency` disables Nagle's algorithm and forwards every `Event::Messages`
+/// batch to the underlying writer as soon as `log_pag` produces it, which is the
+/// right choice for fine-grained computations (e.g. round size 1) where each
+/// batch is tiny and waiting to coalesce would only add latency.
+///
+/// `HighThroughput` also disables Nagle's algorithm, but additionally coalesces
+/// batches into fewer, larger writes (see `CoalescingWriter`), trading a small amount of latency for less per-write
+/// overhead on computations with large rounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportMode {
+    /// `TCP_NODELAY` is set and every batch is written out immediately.
+    LowLatency,
+    /// `TCP_NODELAY` is set and batches are coalesced before being written out.
+    HighThroughput,
+}
+
+impl Default for TransportMode {
+    fn default() -> Self {
+        TransportMode::LowLatency
+    }
+}
+
+/// Tuning knobs for the logging/replay TCP path. `register_logger` reads them
+/// from environment variables (see `from_env`), so that online computations can
+/// be tuned without code changes; they are not taken from any `Config`.
+#[derive(Debug, Clone, Copy)]
+pub struct TransportConfig {
+    /// Whether to coalesce outgoing batches before writing them to the socket.
+    pub mode: TransportMode,
+    /// Once a coalesced batch reaches this many (approximate) bytes, it is flushed.
+    /// It is also always flushed at the next epoch boundary (see `CoalescingWriter`),
+    /// so that `HighThroughput` mode never delays a whole epoch.
+    pub coalesce_bytes: usize,
+}
+
+impl TransportConfig {
+    /// Reads transport tuning from `SNAILTRAIL_TRANSPORT`
+    /// (`"low-latency"` / `"high-throughput"`, defaults to `"low-latency"`) and
+    /// `SNAILTRAIL_COALESCE_BYTES` (defaults to 32KiB).
+    pub fn from_env() -> Self {
+        let mode = match ::std::env::var("SNAILTRAIL_TRANSPORT") {
+            Ok(ref s) if s == "high-throughput" => TransportMode::HighThroughput,
+            _ => TransportMode::LowLatency,
+        };
+
+        let coalesce_bytes = ::std::env::var("SNAILTRAIL_COALESCE_BYTES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(32 * 1024);
+
+        TransportConfig {
+            mode,
+            coalesce_bytes,
+        }
+    }
+}
+
+/// Writers a `push`-driven logger like `log_pag` can tell to force out
+/// anything they're still holding onto once there's nothing left to push --
+/// plain pass-through writers have nothing to flush, so the default is a
+/// no-op; only a buffering writer such as `RateLimitedWriter` overrides it.
+pub trait FlushOnShutdown {
+    /// Blocks until every event this writer is buffering has reached the
+    /// writer it wraps. Call this once, right after the last `push` -- most
+    /// importantly, right after `log_pag`'s final capability-drop
+    /// `Event::Progress`, so throttling can never strand it undelivered.
+    fn flush_blocking(&mut self) {}
+}
+
+impl<T, D, W: Write> FlushOnShutdown for EventWriter<T, D, W> {}
+
+/// Wraps an `EventWriter` so that, in `TransportMode::HighThroughput`, outgoing
+/// `Event::Messages` batches are accumulated and issued as a single, larger
+/// `write` once `coalesce_bytes` is reached, rather than one `write` per batch.
+/// Batches are also flushed whenever an `Event::Progress` comes through, since
+/// `log_pag` only ever emits one of those per epoch boundary -- this bounds
+/// coalescing to within a single epoch.
+/// In `TransportMode::LowLatency`, every batch is forwarded immediately.
+pub struct CoalescingWriter<T, D: ApproxBytes, W: Write> {
+    writer: EventWriter<T, D, W>,
+    mode: TransportMode,
+    coalesce_bytes: usize,
+    pending: Vec<D>,
+    pending_bytes: usize,
+    pending_time: Option<T>,
+}
+
+impl<T, D: ApproxBytes, W: Write> CoalescingWriter<T, D, W> {
+    /// Creates a new `CoalescingWriter` tuned according to `config`.
+    pub fn new(writer: EventWriter<T, D, W>, config: TransportConfig) -> Self {
+        CoalescingWriter {
+            writer,
+            mode: config.mode,
+            coalesce_bytes: config.coalesce_bytes,
+            pending: Vec::new(),
+            pending_bytes: 0,
+            pending_time: None,
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(time) = self.pending_time.take() {
+            if !self.pending.is_empty() {
+                self.writer.push(Event::Messages(
+                    time,
+                    mem::replace(&mut self.pending, Vec::new()),
+                ));
+                self.pending_bytes = 0;
+            }
+        }
+    }
+}
+
+impl<T, D: ApproxBytes, W: Write> FlushOnShutdown for CoalescingWriter<T, D, W> {
+    fn flush_blocking(&mut self) {
+        self.flush();
+    }
+}
+
+impl<T: Clone, D: ApproxBytes, W: Write> EventPusher<T, D> for CoalescingWriter<T, D, W> {
+    fn push(&mut self, event: Event<T, D>) {
+        match event {
+            Event::Messages(time, mut data) if self.mode == TransportMode::HighThroughput => {
+                self.pending_bytes += data.iter().map(ApproxBytes::approx_bytes).sum::<usize>();
+                self.pending_time = Some(time);
+                self.pending.append(&mut data);
+
+                if self.pending_bytes >= self.coalesce_bytes {
+                    self.flush();
+                }
+            }
+            Event::Progress(changes) if self.mode == TransportMode::HighThroughput => {
+                // epoch boundary: don't let a coalesced batch straddle it.
+                self.flush();
+                self.writer.push(Event::Progress(changes));
+            }
+            other => self.writer.push(other),
+        }
+    }
+}
+
+/// Token-bucket configuration for `RateLimitedWriter`. Constructed via
+/// `RateLimiterConfig::from_env`; logging is unthrottled unless it is set.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimiterConfig {
+    /// Sustained fill rate, in bytes/sec, at which the token bucket refills.
+    pub fill_rate_bytes_per_sec: u64,
+    /// Burst capacity, in bytes -- the bucket never holds more tokens than this.
+    pub burst_bytes: u64,
+    /// Size, in bytes, of the in-memory overflow buffer used once tokens run out.
+    pub overflow_capacity_bytes: usize,
+}
+
+impl RateLimiterConfig {
+    /// Reads rate limiting from `SNAILTRAIL_RATE_LIMIT_BYTES_PER_SEC`; logging
+    /// stays unthrottled (`None`) if it isn't set. `SNAILTRAIL_RATE_LIMIT_BURST_BYTES`
+    /// defaults to one second's worth of the fill rate, and
+    /// `SNAILTRAIL_RATE_LIMIT_OVERFLOW_BYTES` to eight seconds' worth.
+    pub fn from_env() -> Option<Self> {
+        let fill_rate_bytes_per_sec: u64 = ::std::env::var("SNAILTRAIL_RATE_LIMIT_BYTES_PER_SEC")
+            .ok()?
+            .parse()
+            .ok()?;
+
+        let burst_bytes = ::std::env::var("SNAILTRAIL_RATE_LIMIT_BURST_BYTES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(fill_rate_bytes_per_sec);
+
+        let overflow_capacity_bytes = ::std::env::var("SNAILTRAIL_RATE_LIMIT_OVERFLOW_BYTES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(fill_rate_bytes_per_sec as usize * 8);
+
+        Some(RateLimiterConfig {
+            fill_rate_bytes_per_sec,
+            burst_bytes,
+            overflow_capacity_bytes,
+        })
+    }
+}
+
+type LogEvent = Event<Pair<u64, Duration>, FlatEventBuffer>;
+
+fn log_event_size(event: &LogEvent) -> usize {
+    match event {
+        Event::Progress(changes) => changes.len() * mem::size_of::<(Pair<u64, Duration>, i64)>(),
+        Event::Messages(_, data) => data.iter().map(ApproxBytes::approx_bytes).sum(),
+    }
+}
+
+/// Token-bucket byte-rate limiter wrapped around the logging writer, so that
+/// a monitored computation never has to give up more than a configured share
+/// of its bandwidth to instrumentation.
+///
+/// Each `push` first tries to consume tokens for the (approximate) serialized
+/// size of the event. Blocking the (nonblocking) logging socket until tokens
+/// are available is not an option, as it would slow down the main computation
+/// -- so instead, once tokens are exhausted, events are spilled into a bounded
+/// overflow buffer. If the overflow buffer is itself full, `Schedule` entries
+/// are stripped out of buffered `Messages` batches first, then whole `Messages`
+/// events are dropped, since both are the highest-volume and most compressible
+/// event kinds; this degrades gracefully under sustained bursts rather than
+/// blocking or buffering without bound. `Progress` events are never dropped:
+/// each one carries a `(frontier, +1)`/`(frontier, -1)` capability delta that
+/// the consuming dataflow's frontier accounting needs in full, so losing even
+/// one would desync that source permanently and hang the downstream probe
+/// rather than merely costing trace fidelity. Under sustained pressure where
+/// only `Progress` events remain buffered, the overflow is allowed to grow
+/// past `overflow_capacity_bytes` rather than violate that guarantee.
+pub struct RateLimitedWriter<P: EventPusher<Pair<u64, Duration>, FlatEventBuffer>> {
+    inner: P,
+    fill_rate: f64,
+    burst: f64,
+    tokens: f64,
+    last_refill: Instant,
+    overflow: VecDeque<LogEvent>,
+    overflow_bytes: usize,
+    overflow_capacity: usize,
+}
+
+impl<P: EventPusher<Pair<u64, Duration>, FlatEventBuffer>> RateLimitedWriter<P> {
+    /// Creates a new `RateLimitedWriter` tuned according to `config`.
+    pub fn new(inner: P, config: RateLimiterConfig) -> Self {
+        RateLimitedWriter {
+            inner,
+            fill_rate: config.fill_rate_bytes_per_sec as f64,
+            burst: config.burst_bytes as f64,
+            tokens: config.burst_bytes as f64,
+            last_refill: Instant::now(),
+            overflow: VecDeque::new(),
+            overflow_bytes: 0,
+            overflow_capacity: config.overflow_capacity_bytes,
+        }
+    }
+
+    fn refill(&mut self) {
+        let elapsed = self.last_refill.elapsed().as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.fill_rate).min(self.burst);
+        self.last_refill = Instant::now();
+    }
+
+    /// Forwards as much of the overflow buffer to `inner` as the current
+    /// token balance allows, oldest first so ordering is preserved.
+    fn drain_overflow(&mut self) {
+        while let Some(event) = self.overflow.front() {
+            let size = log_event_size(event) as f64;
+            if size > self.tokens {
+                break;
+            }
+            self.tokens -= size;
+            let event = self.overflow.pop_front().unwrap();
+            self.overflow_bytes -= log_event_size(&event);
+            self.inner.push(event);
+        }
+    }
+
+    /// Sheds buffered events to make room for `needed` more bytes, cheapest
+    /// first, never touching `Progress` events (see `RateLimitedWriter`'s
+    /// docs). Returns whether enough room could be made without doing so.
+    fn make_room(&mut self, needed: usize) -> bool {
+        while self.overflow_bytes + needed > self.overflow_capacity {
+            // 1. strip `Schedule` entries out of buffered `Messages` batches --
+            //    the cheapest way to shed bytes without losing whole events.
+            let stripped = self.overflow.iter_mut().find_map(|event| {
+                if let Event::Messages(_, data) = event {
+                    data.iter_mut().find_map(|flat| {
+                        let freed = flat.strip_schedule();
+                        if freed > 0 {
+                            Some(freed)
+                        } else {
+                            None
+                        }
+                    })
+                } else {
+                    None
+                }
+            });
+
+            if let Some(freed) = stripped {
+                self.overflow_bytes -= freed;
+                continue;
+            }
+
+            // 2. drop the oldest whole `Messages` event outright.
+            if let Some(pos) = self
+                .overflow
+                .iter()
+                .position(|e| matches!(e, Event::Messages(_, _)))
+            {
+                let event = self.overflow.remove(pos).unwrap();
+                self.overflow_bytes -= log_event_size(&event);
+                continue;
+            }
+
+            // nothing left to shed that isn't a `Progress` event -- give up
+            // rather than drop one of those.
+            return false;
+        }
+
+        true
+    }
+}
+
+impl<P: EventPusher<Pair<u64, Duration>, FlatEventBuffer>>
+    EventPusher<Pair<u64, Duration>, FlatEventBuffer> for RateLimitedWriter<P>
+{
+    fn push(&mut self, event: LogEvent) {
+        self.refill();
+        self.drain_overflow();
+
+        let size = log_event_size(&event);
+
+        // Only send straight through if the overflow buffer is already empty --
+        // otherwise we'd reorder this event ahead of buffered ones.
+        if self.overflow.is_empty() && size as f64 <= self.tokens {
+            self.tokens -= size as f64;
+            self.inner.push(event);
+            return;
+        }
+
+        // `Progress` events are never dropped (see `make_room`'s docs), so one
+        // is always buffered even if that means exceeding `overflow_capacity`.
+        let is_progress = matches!(event, Event::Progress(_));
+        if self.make_room(size) || is_progress {
+            self.overflow_bytes += size;
+            self.overflow.push_back(event);
+        }
+        // else: overflow is full of undroppable `Progress` events and this
+        // incoming event is a `Messages` batch; it is dropped.
+    }
+}
+
+impl<P: EventPusher<Pair<u64, Duration>, FlatEventBuffer>> FlushOnShutdown for RateLimitedWriter<P> {
+    /// Forwards every remaining buffered event to `inner`, bypassing the
+    /// token bucket entirely. `push` only ever drains opportunistically on a
+    /// later call, so without this a throttled run's last buffered events --
+    /// most importantly the final capability-drop `Event::Progress` -- could
+    /// sit in `overflow` forever once `log_pag` stops calling `push`.
+    fn flush_blocking(&mut self) {
+        while let Some(event) = self.overflow.pop_front() {
+            self.overflow_bytes -= log_event_size(&event);
+            self.inner.push(event);
+        }
+    }
+}
+
 /// Listens on 127.0.0.1:8000 and opens `source_peers` sockets from the
 /// computations we're examining (one socket for every worker on the
 /// examined computation).
 /// Adapted from TimelyDataflow examples / https://github.com/utaal/timely-viz
-pub fn open_sockets(source_peers: usize) -> Arc<Mutex<Vec<Option<TcpStream>>>> {
-    let listener = TcpListener::bind("127.0.0.1:8000").unwrap();
-    Arc::new(Mutex::new(
-        (0..source_peers)
-            .map(|_| Some(listener.incoming().next().unwrap().unwrap()))
-            .collect::<Vec<_>>(),
-    ))
+/// Uses `AcceptorConfig::from_env`. A convenience wrapper around
+/// `SocketAcceptor::bind` for callers that only need the initial accept and
+/// not mid-stream reconnection -- the latter requires keeping a
+/// `SocketAcceptor` (and its listener) around, since a new connection has
+/// nowhere to be accepted into once it goes out of scope.
+pub fn open_sockets(
+    source_peers: usize,
+) -> Result<Arc<Mutex<Vec<Option<TcpStream>>>>, AcceptError> {
+    SocketAcceptor::bind("127.0.0.1:8000", source_peers, AcceptorConfig::from_env())
+        .map(|acceptor| acceptor.sockets())
+}
+
+/// Tuning for `SocketAcceptor`: how long to wait for the expected source
+/// peers before giving up, and how often to poll the nonblocking listener
+/// while waiting.
+#[derive(Debug, Clone, Copy)]
+pub struct AcceptorConfig {
+    /// Overall deadline for accepting the expected connections.
+    pub timeout: Duration,
+    /// How long to sleep between readiness checks on the nonblocking
+    /// listener when no connection is available yet.
+    pub poll_interval: Duration,
+}
+
+impl AcceptorConfig {
+    /// Reads `SNAILTRAIL_ACCEPT_TIMEOUT_MS` (default: 30s). The poll
+    /// interval is a fixed, short busy-wait step and isn't user-configurable.
+    pub fn from_env() -> Self {
+        let timeout = std::env::var("SNAILTRAIL_ACCEPT_TIMEOUT_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or_else(|| Duration::from_secs(30));
+
+        AcceptorConfig {
+            timeout,
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+}
+
+impl Default for AcceptorConfig {
+    fn default() -> Self {
+        AcceptorConfig::from_env()
+    }
+}
+
+/// Returned when a `SocketAcceptor` couldn't accept every expected
+/// connection before its configured timeout elapsed.
+#[derive(Debug)]
+pub struct AcceptError {
+    /// Source-peer slot indices that never (re)connected in time.
+    pub missing: Vec<usize>,
+}
+
+impl std::fmt::Display for AcceptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "timed out waiting for source peer(s) to connect: {:?}",
+            self.missing
+        )
+    }
+}
+
+impl Error for AcceptError {}
+
+/// Reads the 8-byte little-endian worker index `register_logger` writes as
+/// the very first bytes of a connection (while it's still in blocking mode,
+/// before the socket is switched to nonblocking for the actual log traffic).
+/// This is how `SocketAcceptor` knows which slot a connection belongs to
+/// without relying on accept order -- which source worker connects first
+/// (or reconnects first, after a drop) isn't something either end controls.
+/// Bounds the read to `timeout` so a peer that connects but stalls before
+/// (or never) finishing its handshake can't block every other peer's accept
+/// behind it -- exactly the single-slow-peer hang the nonblocking accept
+/// loop around this call already exists to avoid.
+fn read_worker_handshake(stream: &mut TcpStream, timeout: Duration) -> std::io::Result<usize> {
+    stream.set_read_timeout(Some(timeout.max(Duration::from_millis(1))))?;
+    let mut buf = [0u8; 8];
+    let result = stream.read_exact(&mut buf);
+    // The timeout above is only for the handshake -- once it's done (or failed),
+    // the stream goes on to be read as an ordinary long-lived replay socket (by
+    // `EventReader`/`ReconnectingReplayer`), which isn't written to expect a
+    // timeout at all, only EOF/reset. Leaving the deadline in place on a
+    // connection that happened to handshake late (`remaining` near zero) would
+    // turn any later lull in the source's log traffic into a spurious error.
+    stream.set_read_timeout(None)?;
+    result?;
+    Ok(u64::from_le_bytes(buf) as usize)
+}
+
+/// A just-accepted connection that hasn't finished announcing its worker
+/// index yet. `bind` polls these alongside the listener itself (both
+/// nonblocking) so one peer that stalls mid-handshake can't hold up another
+/// peer's connection sitting right behind it in the accept backlog -- unlike
+/// a single blocking `read_worker_handshake` call, which would.
+struct PendingHandshake {
+    stream: TcpStream,
+    buf: [u8; 8],
+    filled: usize,
+}
+
+impl PendingHandshake {
+    fn new(stream: TcpStream) -> Self {
+        PendingHandshake {
+            stream,
+            buf: [0; 8],
+            filled: 0,
+        }
+    }
+
+    /// Reads whatever is currently available. `Ok(Some(_))` once the full
+    /// handshake is in, `Ok(None)` if it would still block, `Err` on a
+    /// genuine I/O failure (including EOF before the handshake completed).
+    fn poll(&mut self) -> std::io::Result<Option<usize>> {
+        loop {
+            match self.stream.read(&mut self.buf[self.filled..]) {
+                Ok(0) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "connection closed before handshake completed",
+                    ))
+                }
+                Ok(n) => {
+                    self.filled += n;
+                    if self.filled == self.buf.len() {
+                        return Ok(Some(u64::from_le_bytes(self.buf) as usize));
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Accepts the expected number of source-peer TCP connections for online
+/// capture without blocking the whole inspector on a single slow or failed
+/// peer, and can later splice in a fresh connection for a peer that drops
+/// mid-stream.
+pub struct SocketAcceptor {
+    listener: TcpListener,
+    sockets: Arc<Mutex<Vec<Option<TcpStream>>>>,
+}
+
+impl SocketAcceptor {
+    /// Binds `addr` and accepts `source_peers` connections. Rather than
+    /// `listener.incoming().next().unwrap().unwrap()`'d one at a time --
+    /// which lets a single slow or failed source worker hang every other
+    /// peer -- this polls a nonblocking listener (a poll/epoll-style
+    /// readiness loop) so all expected peers can connect concurrently, in
+    /// whatever order they show up. Each connection is routed to its slot by
+    /// its handshake (see `PendingHandshake`, `register_logger`), not by
+    /// arrival order, so a slow-to-connect source still ends up in the right
+    /// slot -- and a peer that connects but stalls mid-handshake is polled
+    /// the same nonblocking way as the listener itself, so it can't hold up
+    /// any other peer's accept or handshake behind it either. Gives up with
+    /// `AcceptError` listing the slots that never connected once
+    /// `config.timeout` elapses.
+    pub fn bind(
+        addr: &str,
+        source_peers: usize,
+        config: AcceptorConfig,
+    ) -> Result<Self, AcceptError> {
+        let listener = TcpListener::bind(addr).unwrap();
+        listener
+            .set_nonblocking(true)
+            .expect("set_nonblocking call failed");
+
+        let mut sockets: Vec<Option<TcpStream>> = (0..source_peers).map(|_| None).collect();
+        let mut pending: Vec<PendingHandshake> = Vec::new();
+        let start = Instant::now();
+
+        while sockets.iter().filter(|s| s.is_some()).count() < source_peers {
+            // Drain every connection already sitting in the accept backlog
+            // before moving on, rather than picking up just one per
+            // `poll_interval` tick -- a burst of peers connecting around the
+            // same time should all be accepted this same iteration.
+            loop {
+                match listener.accept() {
+                    Ok((stream, _addr)) => {
+                        // Each replayed batch tends to be written out in its
+                        // own `write` call (see `log_pag`/`CoalescingWriter`),
+                        // so waiting for more data to coalesce at the kernel
+                        // level only adds latency here.
+                        stream.set_nodelay(true).expect("set_nodelay call failed");
+                        stream
+                            .set_nonblocking(true)
+                            .expect("set_nonblocking call failed");
+                        pending.push(PendingHandshake::new(stream));
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                    Err(e) => panic!("accept call failed: {}", e),
+                }
+            }
+
+            let mut still_pending = Vec::with_capacity(pending.len());
+            for mut handshake in pending.drain(..) {
+                match handshake.poll() {
+                    Ok(None) => still_pending.push(handshake),
+                    Ok(Some(worker)) if worker < source_peers && sockets[worker].is_none() => {
+                        handshake
+                            .stream
+                            .set_nonblocking(false)
+                            .expect("set_nonblocking call failed");
+                        sockets[worker] = Some(handshake.stream);
+                    }
+                    Ok(Some(worker)) if worker < source_peers => warn!(
+                        "dropping a duplicate handshake for worker {}, which is already connected",
+                        worker
+                    ),
+                    Ok(Some(_)) | Err(_) => warn!(
+                        "dropping a connection with a missing or out-of-range worker handshake"
+                    ),
+                }
+            }
+            pending = still_pending;
+
+            if start.elapsed() >= config.timeout {
+                break;
+            }
+            std::thread::sleep(config.poll_interval);
+        }
+
+        let missing: Vec<usize> = sockets
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.is_none())
+            .map(|(i, _)| i)
+            .collect();
+        if !missing.is_empty() {
+            return Err(AcceptError { missing });
+        }
+
+        Ok(SocketAcceptor {
+            listener,
+            sockets: Arc::new(Mutex::new(sockets)),
+        })
+    }
+
+    /// The accepted sockets, ready to hand to `make_replayers`.
+    pub fn sockets(&self) -> Arc<Mutex<Vec<Option<TcpStream>>>> {
+        Arc::clone(&self.sockets)
+    }
+
+    /// Accepts one more connection and splices it into whichever slot its
+    /// `read_worker_handshake` announces it as. Call this once a `Replayer`
+    /// observes EOF/connection-reset on some slot before the
+    /// `"[st] computation done"` marker -- i.e. a transient drop rather than
+    /// a clean finish -- so a long-running online analysis can pick back up
+    /// across the monitored worker's reconnect instead of aborting the whole
+    /// session. Unlike `bind`, this doesn't take a target slot: nothing on
+    /// this side can know which source worker will reconnect next (or that
+    /// exactly one will, if several drop around the same time), so the
+    /// reconnecting source identifying itself is the only way to route it
+    /// correctly. Returns the slot it was spliced into. Connections with a
+    /// missing, out-of-range or duplicate handshake are dropped and waiting
+    /// goes on; `config.timeout` is measured from the start of the call, so
+    /// time spent on such connections counts toward it.
+    pub fn reconnect(&self, config: AcceptorConfig) -> Result<usize, AcceptError> {
+        let start = Instant::now();
+        loop {
+            match self.listener.accept() {
+                Ok((mut stream, _addr)) => {
+                    stream.set_nodelay(true).expect("set_nodelay call failed");
+                    let source_peers = self.sockets.lock().unwrap().len();
+                    let remaining = config.timeout.saturating_sub(start.elapsed());
+                    match read_worker_handshake(&mut stream, remaining) {
+                        Ok(worker) if worker < source_peers => {
+                            let mut sockets = self.sockets.lock().unwrap();
+                            if sockets[worker].is_some() {
+                                warn!(
+                                    "dropping a duplicate reconnect handshake for worker {}, which is already connected",
+                                    worker
+                                );
+                                continue;
+                            }
+                            sockets[worker] = Some(stream);
+                            return Ok(worker);
+                        }
+                        _ => warn!(
+                            "dropping a reconnect attempt with a missing, out-of-range, or timed-out worker handshake"
+                        ),
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    if start.elapsed() >= config.timeout {
+                        // No specific slot was expected here -- nothing
+                        // (re)connected with a valid handshake before the
+                        // timeout.
+                        return Err(AcceptError { missing: vec![] });
+                    }
+                    std::thread::sleep(config.poll_interval);
+                }
+                Err(e) => panic!("accept call failed: {}", e),
+            }
+        }
+    }
+}
+
+/// A `Progress` batch with exactly one entry and a negative delta is the
+/// capability-drop `log_pag` sends as its very last write before the logged
+/// computation exits (see its "free capabilities" step) -- unlike every
+/// mid-stream `Progress` batch, which always pairs a `(new_frontier, +1)`
+/// with a `(curr_frontier, -1)`. Replay consumers use this shape as the
+/// "done" marker to tell a clean finish apart from a connection drop.
+fn is_final_progress(changes: &[(Pair<u64, Duration>, i64)]) -> bool {
+    matches!(changes, [(_, delta)] if *delta < 0)
+}
+
+/// Wraps a TCP-backed `Replayer` so that a connection drop mid-stream
+/// doesn't end the replayed source's trace. If the inner replayer runs out
+/// of events (EOF/connection-reset) before observing `log_pag`'s final
+/// capability-drop `Event::Progress` (see `is_final_progress`), this polls
+/// slot `index` of `sockets` -- the same `Arc<Mutex<Vec<Option<TcpStream>>>>`
+/// `SocketAcceptor::reconnect` splices a freshly accepted stream into -- and,
+/// once one shows up, rebuilds its inner replayer from it and keeps going.
+/// Without this, a respliced stream sits in that slot unread: nothing else
+/// in this module ever looks at it again once `make_replayers` has taken it.
+/// Note this is only half the story: something still has to notice the drop
+/// and actually call `SocketAcceptor::reconnect`, which means the caller must
+/// keep that `SocketAcceptor` (not just its `sockets()`) alive -- e.g. on a
+/// monitor thread -- for as long as it wants mid-stream recovery to work.
+/// That driver lives with the caller, not in this crate.
+pub struct ReconnectingReplayer {
+    inner: Replayer<ReplayerType>,
+    sockets: Arc<Mutex<Vec<Option<TcpStream>>>>,
+    index: usize,
+    timeout: Duration,
+    waiting_since: Option<Instant>,
+    done: bool,
+}
+
+impl ReconnectingReplayer {
+    /// Wraps `inner` (reading slot `index` of `sockets`) so that it survives
+    /// a mid-stream drop by splicing in whatever fresh connection later
+    /// appears in that slot instead of ending the replay.
+    pub fn new(
+        inner: Replayer<ReplayerType>,
+        sockets: Arc<Mutex<Vec<Option<TcpStream>>>>,
+        index: usize,
+        config: AcceptorConfig,
+    ) -> Self {
+        ReconnectingReplayer {
+            inner,
+            sockets,
+            index,
+            timeout: config.timeout,
+            waiting_since: None,
+            done: false,
+        }
+    }
+
+    /// Takes slot `self.index` if a freshly spliced-in stream is already
+    /// there and rebuilds `self.inner` from it. Never blocks: `next` is
+    /// polled every scheduling round by the single-threaded timely worker,
+    /// so retrying is this function's caller's job, not this function's --
+    /// sleeping in here would stall every other operator on the worker
+    /// along with this one.
+    fn try_reconnect(&mut self) -> bool {
+        if let Some(stream) = self.sockets.lock().unwrap()[self.index].take() {
+            self.inner = EventReader::new(ReplayerType::Tcp(stream));
+            self.waiting_since = None;
+            return true;
+        }
+        false
+    }
+}
+
+impl EventIterator<Pair<u64, Duration>, FlatEventBuffer> for ReconnectingReplayer {
+    fn next(&mut self) -> Option<&Event<Pair<u64, Duration>, FlatEventBuffer>> {
+        loop {
+            match self.inner.next() {
+                Some(event) => {
+                    if let Event::Progress(changes) = event {
+                        if is_final_progress(changes) {
+                            self.done = true;
+                        }
+                    }
+                    return Some(event);
+                }
+                None if self.done => return None,
+                None => {
+                    if self.try_reconnect() {
+                        // loop again, now reading from the freshly spliced inner.
+                        continue;
+                    }
+                    let since = *self.waiting_since.get_or_insert_with(Instant::now);
+                    if since.elapsed() >= self.timeout {
+                        self.done = true;
+                    }
+                    // No reconnect waiting yet this round -- return `None`
+                    // without blocking and let the worker's own scheduling
+                    // loop re-poll us later, the same way it would if we
+                    // simply had no new data.
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Records how far a single source peer's trace has already been consumed,
+/// so a long offline analysis can resume instead of starting over, and an
+/// online capture can recover after a restart. Persist one per source peer
+/// with `save`/`load`, keyed by that peer's index.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Checkpoint {
+    /// Byte offset to seek a peer's `{i}.dump` file to before replaying it.
+    /// `CheckpointedReplayer` only ever saves `0` here -- tracking the exact
+    /// byte a file-backed replay has consumed would have to account for
+    /// `EventReader`'s internal buffering, which this module doesn't attempt.
+    /// A caller that tracks its own precise offset externally may still set
+    /// this to seek past already-processed bytes as a read-time optimization;
+    /// correctness never depends on it, since `frontier` is what's actually
+    /// used to skip already-delivered batches for both file and TCP sources.
+    pub byte_offset: u64,
+    /// The last epoch frontier already delivered for this source.
+    /// `CheckpointedReplayer` uses this both to skip already-seen
+    /// `Event::Progress`/`Event::Messages` batches on resume, and as the
+    /// value it periodically persists as replay advances.
+    pub frontier: Pair<u64, Duration>,
+}
+
+impl Checkpoint {
+    /// Reads a previously persisted checkpoint for source peer `index` from
+    /// `{index}.checkpoint`, or `None` if no checkpoint exists yet (i.e. this
+    /// source's analysis should start from the beginning).
+    pub fn load(index: usize) -> Option<Self> {
+        let path = Path::new(&format!("{}.checkpoint", index)).to_owned();
+        let contents = std::fs::read_to_string(path).ok()?;
+        let mut fields = contents.split_whitespace();
+        let byte_offset = fields.next()?.parse().ok()?;
+        let epoch = fields.next()?.parse().ok()?;
+        let nanos = fields.next()?.parse().ok()?;
+
+        Some(Checkpoint {
+            byte_offset,
+            frontier: Pair::new(epoch, Duration::from_nanos(nanos)),
+        })
+    }
+
+    /// Persists this checkpoint for source peer `index` to `{index}.checkpoint`,
+    /// so a later `make_replayers` call (a restarted analysis, or a resumed
+    /// online session) can pick up from here instead of the beginning.
+    pub fn save(&self, index: usize) -> std::io::Result<()> {
+        let path = Path::new(&format!("{}.checkpoint", index)).to_owned();
+        std::fs::write(
+            path,
+            format!(
+                "{} {} {}",
+                self.byte_offset,
+                self.frontier.first,
+                self.frontier.second.as_nanos()
+            ),
+        )
+    }
+}
+
+/// How many `Event::Progress` batches `CheckpointedReplayer` lets pass
+/// between persisting its current frontier to disk. Saving on every batch
+/// would add a file write to the hot replay path; this bounds that cost
+/// while keeping the persisted checkpoint reasonably fresh.
+const CHECKPOINT_SAVE_INTERVAL: usize = 64;
+
+/// Wraps a replayer (anything that implements `EventIterator`, whether a raw
+/// `Replayer<R>` or another wrapper such as `ReconnectingReplayer`) in two
+/// ways: on construction, it resumes from `checkpoint` by skipping any
+/// `Event::Progress`/`Event::Messages` batch at or before the checkpoint's
+/// frontier (already delivered in a prior session); as replay advances past
+/// that point, it periodically persists its own frontier as a fresh
+/// `Checkpoint` (see `Checkpoint::save`), so a later run -- a restarted
+/// offline analysis, or an online capture recovering after a crash -- can
+/// resume from here in turn.
+pub struct CheckpointedReplayer<I> {
+    inner: I,
+    index: usize,
+    resume_from: Pair<u64, Duration>,
+    frontier: Pair<u64, Duration>,
+    since_save: usize,
+}
+
+impl<I> CheckpointedReplayer<I> {
+    /// Wraps `inner` (replaying source peer `index`) so that it resumes from
+    /// `checkpoint` and periodically persists its own progress back to that
+    /// peer's checkpoint file.
+    pub fn new(inner: I, index: usize, checkpoint: Checkpoint) -> Self {
+        CheckpointedReplayer {
+            inner,
+            index,
+            resume_from: checkpoint.frontier,
+            frontier: checkpoint.frontier,
+            since_save: 0,
+        }
+    }
+}
+
+impl<I: EventIterator<Pair<u64, Duration>, FlatEventBuffer>>
+    EventIterator<Pair<u64, Duration>, FlatEventBuffer> for CheckpointedReplayer<I>
+{
+    fn next(&mut self) -> Option<&Event<Pair<u64, Duration>, FlatEventBuffer>> {
+        loop {
+            match self.inner.next() {
+                None => return None,
+                Some(event) => {
+                    // The final wrap-up batch (see `log_pag`/`is_final_progress`)
+                    // only ever carries a capability-drop, with no matching
+                    // `+1` -- if the source had already finished at or before
+                    // the checkpoint's frontier, the `already_seen` check below
+                    // would otherwise swallow it.
+                    // It must always be delivered: it's what tells the
+                    // consuming dataflow this source is done.
+                    let is_final = matches!(event, Event::Progress(changes) if is_final_progress(changes));
+
+                    // Only batches at or before the checkpoint we resumed from
+                    // were delivered in a prior session; comparing against the
+                    // running `self.frontier` would also skip each live
+                    // `Messages` batch sent at the frontier just advanced to.
+                    let already_seen = !is_final
+                        && match event {
+                            Event::Progress(changes) => {
+                                changes.iter().all(|(t, _)| *t <= self.resume_from)
+                            }
+                            Event::Messages(t, _) => *t <= self.resume_from,
+                        };
+
+                    if already_seen {
+                        continue;
+                    }
+
+                    // Advance (and periodically persist) our frontier from
+                    // the new epoch a mid-stream `Event::Progress` batch
+                    // advances to. The final wrap-up batch never carries a
+                    // `+1`, so it's excluded from this by `find` below --
+                    // nothing further is resumable once that's delivered.
+                    if let Event::Progress(changes) = event {
+                        if let Some((new_frontier, _)) =
+                            changes.iter().find(|(_, delta)| *delta > 0)
+                        {
+                            if *new_frontier > self.frontier {
+                                self.frontier = *new_frontier;
+                                self.since_save += 1;
+                                if self.since_save >= CHECKPOINT_SAVE_INTERVAL {
+                                    self.since_save = 0;
+                                    let checkpoint = Checkpoint {
+                                        byte_offset: 0,
+                                        frontier: self.frontier,
+                                    };
+                                    if let Err(e) = checkpoint.save(self.index) {
+                                        warn!(
+                                            "failed to save checkpoint for source {}: {}",
+                                            self.index, e
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    return Some(event);
+                }
+            }
+        }
+    }
 }
 
-// @TODO Currently, the computation runs best with worker_peers == source_peers.
-// It might be worth investigating how replaying could benefit from worker_peers > source_peers.
-// @TODO TCP stream optimization might be necessary (e.g. smarter consumption of batches)
 /// Construct replayers that read data from sockets or file and can stream it into
-/// timely dataflow. If `Some(sockets)` is passed, the replayers assume an online setting.
+/// timely dataflow. If `Some(sockets)` is passed, the replayers assume an online setting,
+/// and each is additionally wrapped in a `ReconnectingReplayer` against the same
+/// `sockets` pool, so a source connection that drops mid-stream doesn't end that
+/// peer's replay -- see `SocketAcceptor::reconnect` for splicing the fresh
+/// connection back into the slot this polls. `ReconnectingReplayer` only consumes
+/// that slot, though -- nothing here ever calls `reconnect`, so `sockets` must come
+/// from a `SocketAcceptor` the caller kept alive (and is driving `reconnect` on,
+/// e.g. from a monitor thread watching for dropped peers) rather than from
+/// `open_sockets`, whose whole point is discarding that acceptor once the initial
+/// accept is done.
+/// Every replayer is wrapped in a `CheckpointedReplayer`: if `checkpoints` has an
+/// entry for its source peer, it resumes from that `Checkpoint` (see
+/// `Checkpoint::load`) instead of starting from the beginning -- file-backed
+/// replayers also seek past `byte_offset` before replaying -- and regardless of
+/// whether it resumed or started fresh, it periodically persists its own
+/// progress back to that peer's checkpoint file as replay advances, so a later
+/// run (a restarted offline analysis, or a recovering online capture) can in
+/// turn resume from there.
+///
+/// `worker_peers` no longer has to equal `source_peers`: each analysis worker is
+/// handed its round-robin share of source readers (`source_index % worker_peers ==
+/// worker_index`), so fewer analysis workers than source peers means some workers
+/// read from more than one source, and more analysis workers than source peers
+/// means some workers read from none. Because of that, PAG construction can no
+/// longer assume a replayer's *analysis* worker matches its *source* worker --
+/// callers must replay this function's output through `exchange_by_source_worker`
+/// rather than feeding it straight to `pag::create_pag`, so that the stable
+/// source-worker key each event carries (not the reader it happened to arrive
+/// over) decides which analysis worker ends up holding it.
 /// Adapted from TimelyDataflow examples / https://github.com/utaal/timely-viz
 pub fn make_replayers(
     worker_index: usize,
     worker_peers: usize,
     source_peers: usize,
     sockets: Option<Arc<Mutex<Vec<Option<TcpStream>>>>>,
-) -> Vec<Replayer<ReplayerType>> {
-    assert!(source_peers == worker_peers, "fix exchange after logrecord creation for this to work");
-
+    checkpoints: Option<&[Checkpoint]>,
+) -> Vec<Box<dyn EventIterator<Pair<u64, Duration>, FlatEventBuffer>>> {
     info!(
-        "Creating replayers\tworker index: {}, worker peers: {}, source peers: {}, online: {}",
+        "Creating replayers\tworker index: {}, worker peers: {}, source peers: {}, online: {}, resuming: {}",
         worker_index,
         worker_peers,
         source_peers,
-        sockets.is_some()
+        sockets.is_some(),
+        checkpoints.is_some()
     );
 
     if let Some(sockets) = sockets {
         // online
+        let acceptor_config = AcceptorConfig::from_env();
         sockets
             .lock()
             .unwrap()
             .iter_mut()
             .enumerate()
             .filter(|(i, _)| *i % worker_peers == worker_index)
-            .map(move |(_, s)| s.take().unwrap())
-            .map(|r| EventReader::new(ReplayerType::Tcp(r)))
+            .map(|(i, s)| (i, s.take().unwrap()))
+            .map(|(i, r)| {
+                let reader = EventReader::new(ReplayerType::Tcp(r));
+                let reconnecting =
+                    ReconnectingReplayer::new(reader, Arc::clone(&sockets), i, acceptor_config);
+                let checkpoint = checkpoints.and_then(|cs| cs.get(i)).copied().unwrap_or_default();
+                Box::new(CheckpointedReplayer::new(reconnecting, i, checkpoint))
+                    as Box<dyn EventIterator<Pair<u64, Duration>, FlatEventBuffer>>
+            })
             .collect::<Vec<_>>()
     } else {
         // from file
@@ -104,31 +1429,131 @@ pub fn make_replayers(
                 let name = format!("{:?}.dump", i);
                 let path = Path::new(&name);
 
-                match File::open(&path) {
+                let mut file = match File::open(&path) {
                     Err(why) => panic!("couldn't open. {}", why.description()),
                     Ok(file) => file,
-                }
+                };
+
+                let checkpoint = checkpoints.and_then(|cs| cs.get(i)).copied().unwrap_or_default();
+                file.seek(SeekFrom::Start(checkpoint.byte_offset))
+                    .expect("failed to seek to checkpoint offset");
+
+                (i, file, checkpoint)
+            })
+            .map(|(i, f, checkpoint)| {
+                let reader = EventReader::new(ReplayerType::File(f));
+                Box::new(CheckpointedReplayer::new(reader, i, checkpoint))
+                    as Box<dyn EventIterator<Pair<u64, Duration>, FlatEventBuffer>>
             })
-            .map(|f| EventReader::new(ReplayerType::File(f)))
             .collect::<Vec<_>>()
     }
 }
 
+/// A single decoded log event, paired with the source-worker index it
+/// originated from (the `worker` `FlatEventBuffer::push` recorded it under,
+/// i.e. the logged computation's own worker index). `exchange_by_source_worker`
+/// uses that index as a stable routing key, so which *analysis* worker ends up
+/// holding a given `LogRecord` no longer depends on which replayer connection
+/// it was read over.
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    /// Epoch-relative time the event was logged at.
+    pub time: Duration,
+    /// The originating source computation's own worker index.
+    pub source_worker: usize,
+    /// The decoded event.
+    pub event: TimelyEvent,
+}
+
+/// Replays `replayers` (as returned by `make_replayers`) into `scope` and
+/// re-exchanges the individual events they carry by `source_worker`. This is
+/// the distribution stage `make_replayers`' relaxed `worker_peers`/
+/// `source_peers` ratio requires: once a single analysis worker can hold
+/// readers for an arbitrary subset of source peers (or none at all), PAG
+/// construction can no longer rely on a replayer's analysis worker matching
+/// its source worker, so it must instead consume a stream that's been
+/// re-partitioned by each event's stable source-worker key. Callers (e.g.
+/// `pag::create_pag`) should build their dataflow on top of the returned
+/// stream rather than replaying `make_replayers`' readers directly.
+///
+/// This re-introduces a per-event allocation at exactly the boundary the
+/// region-allocated `FlatEventBuffer` exists to avoid: `flat_map` explodes
+/// every batch back into one owned `LogRecord`/`TimelyEvent` per event (via
+/// `to_owned_event`, re-copying addrs/text/message payloads) before
+/// `exchange` can route by `source_worker`. `exchange` -- and any pact in
+/// general -- needs an owned, `Send`-able item per key to route, so it can't
+/// operate on borrowed `FlatEventView`s into a buffer that's about to be
+/// handed to a different worker; a by-reference decode path only works when
+/// the consumer stays on the thread that owns the buffer. Avoiding this
+/// would mean exchanging whole `FlatEventBuffer`s by a key derived from their
+/// contents (all-or-nothing per batch, not per event), which isn't what this
+/// function does.
+pub fn exchange_by_source_worker<S>(
+    scope: &mut S,
+    replayers: Vec<Box<dyn EventIterator<Pair<u64, Duration>, FlatEventBuffer>>>,
+) -> Stream<S, LogRecord>
+where
+    S: Scope<Timestamp = Pair<u64, Duration>>,
+{
+    replayers
+        .replay_into(scope)
+        .flat_map(|buffer| {
+            buffer
+                .iter()
+                .map(|(time, source_worker, view)| LogRecord {
+                    time,
+                    source_worker,
+                    event: view.to_owned_event(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .exchange(|record| record.source_worker as u64)
+}
+
 /// Logging of events to TCP or file.
 /// For live analysis, provide `SNAILTRAIL_ADDR` as env variable.
 /// Else, the computation will log to file for later replay.
+///
+/// Transport tuning (nodelay, write coalescing) is picked up from
+/// `TransportConfig::from_env`, and an optional byte-rate limit from
+/// `RateLimiterConfig::from_env` -- see their docs for the relevant env vars.
 pub fn register_logger(worker: &mut Worker<Generic>) {
+    let transport = TransportConfig::from_env();
+    let rate_limit = RateLimiterConfig::from_env();
+
     if let Ok(addr) = ::std::env::var("SNAILTRAIL_ADDR") {
-        if let Ok(stream) = TcpStream::connect(&addr) {
+        if let Ok(mut stream) = TcpStream::connect(&addr) {
+            // Handshake: announce this source's own worker index as the
+            // very first bytes, while the socket is still in blocking mode,
+            // so `SocketAcceptor::bind`/`reconnect` on the other end can
+            // route this connection to the right replayer slot instead of
+            // guessing from accept order (see `read_worker_handshake`).
+            stream
+                .write_all(&(worker.index() as u64).to_le_bytes())
+                .expect("failed to send worker handshake");
+
             // SnailTrail should be able to keep up with an online computation.
             // If batch sizes are too large, they should be buffered. Blocking the
             // TCP connection is not an option as it slows down the main computation.
             stream
                 .set_nonblocking(true)
                 .expect("set_nonblocking call failed");
+            // Each flushed batch is already a deliberately-sized write (either
+            // immediate, or coalesced by `CoalescingWriter`), so we never want
+            // the kernel delaying it further to wait for more data.
+            stream.set_nodelay(true).expect("set_nodelay call failed");
 
-            let writer = EventWriter::new(stream);
-            unsafe { log_pag(worker, writer); }
+            let writer = CoalescingWriter::new(EventWriter::new(stream), transport);
+            if let Some(rate_limit) = rate_limit {
+                let writer = RateLimitedWriter::new(writer, rate_limit);
+                unsafe {
+                    log_pag(worker, writer);
+                }
+            } else {
+                unsafe {
+                    log_pag(worker, writer);
+                }
+            }
         } else {
             panic!("Could not connect logging stream to: {:?}", addr);
         }
@@ -139,16 +1564,16 @@ pub fn register_logger(worker: &mut Worker<Generic>) {
             Err(why) => panic!("couldn't create {}: {}", path.display(), why.description()),
             Ok(file) => file,
         };
+        // Coalescing/nodelay are TCP-path concerns; file-backed offline logging
+        // always writes batches out as they're produced.
         let writer = EventWriter::new(file);
-        unsafe { log_pag(worker, writer); }
+        unsafe {
+            log_pag(worker, writer);
+        }
     }
 }
 
 // @TODO: further describe contract between log_pag and SnailTrail; mark as unsafe
-// @TODO: for triangles query with round size == 1, the computation is slowed down by TCP.
-//        A reason for this might be the overhead in creating TCP packets, so it might be
-//        worthwhile investigating the reintroduction of batching for very small computation
-//        rounds.
 /// Registers a `TimelyEvent` logger which outputs relevant log events for PAG construction.
 /// 1. Only relevant events are written to `writer`.
 /// 2. Using `Text` events as markers, logged events are written out at one time per epoch.
@@ -165,9 +1590,9 @@ pub fn register_logger(worker: &mut Worker<Generic>) {
 /// 3. (optional) After the last round, the end of the computation should be logged:
 ///    `"[st] computation done"`
 /// Failing to do so might have unexpected effects on the PAG creation.
-unsafe fn log_pag<W: 'static + Write>(
+unsafe fn log_pag<W: 'static + EventPusher<Pair<u64, Duration>, FlatEventBuffer> + FlushOnShutdown>(
     worker: &mut Worker<Generic>,
-    mut writer: EventWriter<Pair<u64, Duration>, (Duration, usize, TimelyEvent), W>,
+    mut writer: W,
 ) {
     // first real frontier, used for setting up the computation
     // (`Operates` et al.)
@@ -184,7 +1609,9 @@ unsafe fn log_pag<W: 'static + Write>(
     // buffer of relevant events for a batch. As a batch only ever belongs
     // to a single epoch (epoch markers only appear at the beginning of a batch),
     // we don't have to keep track of times for batch elements.
-    let mut buffer = Vec::new();
+    // Region-allocated so a whole batch costs a handful of allocations rather
+    // than one per logged event.
+    let mut buffer = FlatEventBuffer::new();
 
     // 1st: marker that computation has ended
     // 2nd: capabilities have been dropped; no further messages
@@ -218,7 +1645,7 @@ unsafe fn log_pag<W: 'static + Write>(
                             allow_frontier_update = false;
                         }
 
-                        buffer.push(tuple);
+                        buffer.push(tuple.0, tuple.1, tuple.2);
                     }
                     Operates(_) | Channels(_) => {
                         if !wrap_up.1 {
@@ -232,7 +1659,7 @@ unsafe fn log_pag<W: 'static + Write>(
 
                         // the tuple is provided to the computation at a 0ns data timestamp,
                         // and (0, 0ns) time (see below).
-                        buffer.push((curr_frontier.second, tuple.1, tuple.2));
+                        buffer.push(curr_frontier.second, tuple.1, tuple.2);
                     }
                     // Text events mark epochs in the computation. They are always the first
                     // in their batch, so a single batch is never split into multiple epochs.
@@ -267,10 +1694,19 @@ unsafe fn log_pag<W: 'static + Write>(
                         allow_frontier_update = true;
 
                         // flush out remaining elements
-                        if buffer.len() > 0 {
-                            trace!("w{} flush@{:?}: count: {} | total: {}", index, curr_frontier, buffer.len(), total);
+                        if !buffer.is_empty() {
+                            trace!(
+                                "w{} flush@{:?}: count: {} | total: {}",
+                                index,
+                                curr_frontier,
+                                buffer.len(),
+                                total
+                            );
                             total += buffer.len();
-                            writer.push(Event::Messages(curr_frontier.clone(), std::mem::replace(&mut buffer, Vec::new())));
+                            writer.push(Event::Messages(
+                                curr_frontier.clone(),
+                                vec![std::mem::replace(&mut buffer, FlatEventBuffer::new())],
+                            ));
                         }
                     }
                 }
@@ -290,16 +1726,325 @@ unsafe fn log_pag<W: 'static + Write>(
                 );
 
                 // write out remaining messages
-                if buffer.len() > 0 {
-                    writer.push(Event::Messages(curr_frontier.clone(), buffer.clone()));
+                if !buffer.is_empty() {
+                    writer.push(Event::Messages(curr_frontier.clone(), vec![buffer.clone()]));
                 }
 
                 // free capabilities
                 writer.push(Event::Progress(vec![(curr_frontier.clone(), -1)]));
 
+                // This is the last `push` this logger will ever make, so
+                // force out anything a throttled writer is still holding --
+                // above all, the capability-drop just pushed -- rather than
+                // leaving it stranded in an overflow buffer nobody drains
+                // again (see `RateLimitedWriter::flush_blocking`).
+                writer.flush_blocking();
+
                 // 1st to false so that marker isn't processed multiple times.
                 // 2nd to true so that no further `Event::Messages` will be sent.
                 wrap_up = (false, true);
             }
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use timely::dataflow::operators::{Inspect, Probe};
+
+    /// Collects pushed events for assertions, standing in for the real
+    /// `EventWriter` that `RateLimitedWriter` wraps in production.
+    struct RecordingPusher(Vec<LogEvent>);
+
+    impl EventPusher<Pair<u64, Duration>, FlatEventBuffer> for RecordingPusher {
+        fn push(&mut self, event: LogEvent) {
+            self.0.push(event);
+        }
+    }
+
+    fn progress(epoch: u64) -> LogEvent {
+        Event::Progress(vec![(Pair::new(epoch, Duration::default()), 1)])
+    }
+
+    fn messages(epoch: u64) -> LogEvent {
+        let mut buf = FlatEventBuffer::new();
+        buf.push(
+            Duration::default(),
+            0,
+            TimelyEvent::Text(format!("padding-{}", epoch)),
+        );
+        Event::Messages(Pair::new(epoch, Duration::default()), vec![buf])
+    }
+
+    #[test]
+    fn rate_limited_writer_never_drops_progress() {
+        // Starve the bucket so every push goes through the overflow path,
+        // and cap the overflow so small that it's immediately exceeded.
+        let mut writer = RateLimitedWriter::new(
+            RecordingPusher(Vec::new()),
+            RateLimiterConfig {
+                fill_rate_bytes_per_sec: 0,
+                burst_bytes: 0,
+                overflow_capacity_bytes: 1,
+            },
+        );
+
+        for epoch in 0..8 {
+            writer.push(messages(epoch));
+        }
+        for epoch in 0..8 {
+            writer.push(progress(epoch));
+        }
+
+        let buffered_progress = writer
+            .overflow
+            .iter()
+            .filter(|e| matches!(e, Event::Progress(_)))
+            .count();
+        assert_eq!(
+            buffered_progress, 8,
+            "every buffered Progress event must survive, even past overflow_capacity_bytes"
+        );
+
+        // Buffered is not delivered: starved of tokens, none of this would
+        // ever reach `inner` without a forced flush -- exactly what `log_pag`
+        // does once it has nothing left to push.
+        writer.flush_blocking();
+        assert!(
+            writer.overflow.is_empty(),
+            "flush_blocking must drain everything, bypassing the token bucket"
+        );
+
+        let delivered_progress = writer
+            .inner
+            .0
+            .iter()
+            .filter(|e| matches!(e, Event::Progress(_)))
+            .count();
+        assert_eq!(
+            delivered_progress, 8,
+            "every buffered Progress event must actually reach the writer, not just survive in overflow"
+        );
+    }
+
+    /// A connected `TcpStream` pair's client side, suitable for a
+    /// `make_replayers` distribution test -- these tests only exercise which
+    /// socket slot each analysis worker claims, never actual replay.
+    fn connected_stream() -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).unwrap();
+        let _server_side = listener.accept().unwrap();
+        client
+    }
+
+    fn source_sockets(source_peers: usize) -> Arc<Mutex<Vec<Option<TcpStream>>>> {
+        Arc::new(Mutex::new(
+            (0..source_peers)
+                .map(|_| Some(connected_stream()))
+                .collect(),
+        ))
+    }
+
+    /// The number of replayers `make_replayers` hands each analysis worker
+    /// under round-robin distribution (`source_index % worker_peers ==
+    /// worker_index`), sharing one socket pool the way the real online path
+    /// does: every worker draws from the same `Arc<Mutex<..>>`, so each
+    /// source socket is claimed by exactly one worker.
+    fn reader_counts(worker_peers: usize, source_peers: usize) -> Vec<usize> {
+        let sockets = source_sockets(source_peers);
+        (0..worker_peers)
+            .map(|worker_index| {
+                make_replayers(
+                    worker_index,
+                    worker_peers,
+                    source_peers,
+                    Some(Arc::clone(&sockets)),
+                    None,
+                )
+                .len()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn make_replayers_handles_fewer_source_peers_than_workers() {
+        // source_peers < worker_peers: some analysis workers get no reader.
+        let counts = reader_counts(4, 2);
+        assert_eq!(counts.iter().sum::<usize>(), 2);
+        assert_eq!(counts.iter().filter(|&&c| c == 0).count(), 2);
+    }
+
+    #[test]
+    fn make_replayers_handles_more_source_peers_than_workers() {
+        // source_peers > worker_peers: some analysis workers read more than one.
+        let counts = reader_counts(2, 5);
+        assert_eq!(counts.iter().sum::<usize>(), 5);
+        assert!(counts.iter().any(|&c| c > 1));
+    }
+
+    #[test]
+    fn make_replayers_handles_equal_source_peers_and_workers() {
+        let counts = reader_counts(3, 3);
+        assert_eq!(counts, vec![1, 1, 1]);
+    }
+
+    /// A fixed sequence of events, standing in for the real `Replayer<R>`
+    /// `CheckpointedReplayer` wraps in production.
+    struct VecReplayer {
+        events: Vec<LogEvent>,
+        index: usize,
+    }
+
+    impl EventIterator<Pair<u64, Duration>, FlatEventBuffer> for VecReplayer {
+        fn next(&mut self) -> Option<&LogEvent> {
+            let event = self.events.get(self.index)?;
+            self.index += 1;
+            Some(event)
+        }
+    }
+
+    fn final_marker(epoch: u64) -> LogEvent {
+        Event::Progress(vec![(Pair::new(epoch, Duration::default()), -1)])
+    }
+
+    #[test]
+    fn checkpointed_replayer_delivers_final_progress_marker() {
+        // A realistic sequence: progress to epoch 1, a batch of messages,
+        // progress to epoch 2, more messages, then `log_pag`'s final
+        // capability-drop at the frontier it never further advances past.
+        let inner = VecReplayer {
+            events: vec![
+                progress(1),
+                messages(1),
+                progress(2),
+                messages(2),
+                final_marker(2),
+            ],
+            index: 0,
+        };
+
+        let mut replayer = CheckpointedReplayer::new(inner, 0, Checkpoint::default());
+        let mut seen = 0;
+        let mut saw_final = false;
+        while let Some(event) = replayer.next() {
+            seen += 1;
+            if let Event::Progress(changes) = event {
+                if is_final_progress(changes) {
+                    saw_final = true;
+                }
+            }
+        }
+
+        assert_eq!(seen, 5, "no event should be dropped as already-seen");
+        assert!(
+            saw_final,
+            "the final capability-drop marker must reach the consumer, or probe.done() never fires"
+        );
+    }
+
+    const EXCHANGE_TEST_MESSAGES_PER_SOURCE: usize = 3;
+
+    /// A replayer standing in for one source peer: a batch of `Text` events
+    /// tagged with `source_worker` (as `FlatEventBuffer::push` would for a
+    /// real logged computation), bracketed by the progress/final-marker
+    /// sequence `exchange_by_source_worker`'s `Replay` plumbing needs to
+    /// deliver it and then let the dataflow drain.
+    fn exchange_test_replayer(
+        source_worker: usize,
+    ) -> Box<dyn EventIterator<Pair<u64, Duration>, FlatEventBuffer>> {
+        let mut buf = FlatEventBuffer::new();
+        for n in 0..EXCHANGE_TEST_MESSAGES_PER_SOURCE {
+            buf.push(
+                Duration::default(),
+                source_worker,
+                TimelyEvent::Text(format!("w{}-{}", source_worker, n)),
+            );
+        }
+
+        Box::new(VecReplayer {
+            events: vec![
+                // Like `log_pag`'s own first progress batch, this also drops
+                // the replay operator's initial capability at the default
+                // `(0, 0ns)` time; otherwise the probe would never be done.
+                Event::Progress(vec![
+                    (Pair::new(1, Duration::default()), 1),
+                    (Pair::new(0, Duration::default()), -1),
+                ]),
+                Event::Messages(Pair::new(1, Duration::default()), vec![buf]),
+                final_marker(1),
+            ],
+            index: 0,
+        })
+    }
+
+    /// Runs `exchange_by_source_worker` across `worker_peers` analysis
+    /// workers, each holding its round-robin share (see `make_replayers`) of
+    /// `source_peers` stub replayers, and returns how many `LogRecord`s each
+    /// `source_worker` key's events were received under -- regardless of
+    /// which analysis worker ends up running `record.source_worker`'s
+    /// replayer.
+    fn exchange_received_per_source(worker_peers: usize, source_peers: usize) -> HashMap<usize, usize> {
+        let counts: Arc<Mutex<HashMap<usize, usize>>> = Arc::new(Mutex::new(HashMap::new()));
+        let counts_for_workers = Arc::clone(&counts);
+
+        timely::execute::execute(timely::Config::process(worker_peers), move |worker| {
+            let worker_index = worker.index();
+            let replayers: Vec<Box<dyn EventIterator<Pair<u64, Duration>, FlatEventBuffer>>> =
+                (0..source_peers)
+                    .filter(|i| i % worker_peers == worker_index)
+                    .map(exchange_test_replayer)
+                    .collect();
+
+            let counts = Arc::clone(&counts_for_workers);
+            let probe = worker.dataflow(|scope| {
+                exchange_by_source_worker(scope, replayers)
+                    .inspect(move |record| {
+                        *counts.lock().unwrap().entry(record.source_worker).or_insert(0) += 1;
+                    })
+                    .probe()
+            });
+
+            while !probe.done() {
+                worker.step();
+            }
+        })
+        .unwrap();
+
+        Arc::try_unwrap(counts).unwrap().into_inner().unwrap()
+    }
+
+    /// Every source peer's events must reach *some* analysis worker intact
+    /// and still tagged with their own `source_worker`, no matter how
+    /// `source_peers` and `worker_peers` compare -- this is the actual
+    /// distribution stage the relaxed ratio in `make_replayers` depends on.
+    fn assert_exchange_preserves_every_source(worker_peers: usize, source_peers: usize) {
+        let counts = exchange_received_per_source(worker_peers, source_peers);
+        for source_worker in 0..source_peers {
+            assert_eq!(
+                counts.get(&source_worker).copied().unwrap_or(0),
+                EXCHANGE_TEST_MESSAGES_PER_SOURCE,
+                "source worker {} lost events across the exchange (worker_peers={}, source_peers={})",
+                source_worker,
+                worker_peers,
+                source_peers
+            );
+        }
+    }
+
+    #[test]
+    fn exchange_by_source_worker_handles_fewer_source_peers_than_workers() {
+        assert_exchange_preserves_every_source(4, 2);
+    }
+
+    #[test]
+    fn exchange_by_source_worker_handles_more_source_peers_than_workers() {
+        assert_exchange_preserves_every_source(2, 5);
+    }
+
+    #[test]
+    fn exchange_by_source_worker_handles_equal_source_peers_and_workers() {
+        assert_exchange_preserves_every_source(3, 3);
+    }
+}